@@ -1,62 +1,379 @@
 use std::{
-    fmt::Debug,
+    collections::{HashSet, VecDeque},
     ops::{Deref, RangeInclusive},
 };
 
-use object::{Object, ObjectSection, ObjectSymbol, SymbolIterator};
+use object::{Object, ObjectSection, ObjectSymbol, RelocationTarget, SectionIndex, SymbolIterator};
+
+use memmap::MemMap;
 
 fn main() {
-    let path = std::env::args()
-        .nth(1)
-        .expect("requires argument (path to elf)");
+    // Pull out recognized flags wherever they fall in argv, position-independent
+    // the same way `--sort`/`--depth`/etc. already were meant to be; whatever's
+    // left over is the ELF path, regardless of where it falls.
+    let mut sort_size = false;
+    let mut depth = 3;
+    let mut show_dead = false;
+    let mut chip = None;
+    let mut memory_script = None;
+    let mut path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sort" => sort_size = args.next().as_deref() == Some("size"),
+            "--depth" => depth = args.next().and_then(|v| v.parse().ok()).unwrap_or(depth),
+            "--dead" => show_dead = true,
+            "--chip" => chip = args.next(),
+            "--memory" => memory_script = args.next(),
+            _ if path.is_none() => path = Some(arg),
+            _ => panic!("unexpected argument: {arg}"),
+        }
+    }
+    let path = path.expect("requires argument (path to elf)");
+
+    let map = match memory_script {
+        Some(path) => {
+            let src = std::fs::read_to_string(&path).expect("read --memory linker script");
+            MemMap::from_linker_script(&src).unwrap_or_else(|e| panic!("{path}: {e}"))
+        }
+        None => {
+            let chip = chip.as_deref().unwrap_or("esp32c3");
+            MemMap::for_chip(chip).unwrap_or_else(|| {
+                panic!("unknown --chip {chip:?}; pass --memory <linker.x> instead")
+            })
+        }
+    };
 
     let data = std::fs::read(path).expect("read");
 
     let object = object::File::parse(data.as_slice()).expect("parse");
 
-    print_alloc(&object, DRAM, "DRAM");
-    print_alloc(&object, IRAM, "IRAM");
+    let reach = Reachability::compute(&object);
+
+    let opts = ReportOpts {
+        sort_size,
+        depth,
+        show_dead,
+    };
+
+    let mut overflowed = false;
+    let mut ram_total = Bytes::default();
+    let mut flash_total = Bytes::default();
+    for (name, region) in map.regions() {
+        match print_alloc(&object, &reach, region, name, &opts) {
+            Ok(totals) => {
+                ram_total += totals.ram;
+                flash_total += totals.flash;
+            }
+            Err(overflow) => {
+                eprintln!("error: {overflow}");
+                overflowed = true;
+            }
+        }
+    }
+    if overflowed {
+        std::process::exit(1);
+    }
+
+    println!(
+        "{:>21}\t0x{ram_total:05x}\t({ram_total:6})\t—\tRAM total (all regions)",
+        "RAM"
+    );
+    println!(
+        "{:>21}\t0x{flash_total:05x}\t({flash_total:6})\t—\tflash/load image total",
+        "DROM"
+    );
 }
 
-fn print_alloc<'data: 'file, 'file, 'str, Object>(object: &'file Object, r: Region, name: &'str str)
-where
-    Object: object::Object<'data, 'file> + 'file,
-    Object::Symbol: Debug,
-{
-    let mut syms = object
-        .symbols()
-        .filter(|sym| r.contains(&sym.address()))
-        .filter_map(|sym| sym.name().map(|name| (sym.address(), name, sym)).ok())
-        .collect::<Vec<_>>();
+/// RAM vs. flash accounting for one region: `ram` is the region's runtime
+/// footprint (everything mapped into the VMA, including `.bss`/`.uninit`),
+/// `flash` is just the subset that also needs a load image (`PROGBITS`
+/// sections with file contents — `.data` but not `.bss`).
+struct RegionTotals {
+    ram: Bytes,
+    flash: Bytes,
+}
+
+/// CLI knobs shared by every region report.
+struct ReportOpts {
+    /// `--sort size`: order the per-symbol tree by size instead of name.
+    sort_size: bool,
+    /// `--depth N`: collapse the per-symbol tree below this many path
+    /// segments (crate/module/.../symbol) into their containing node.
+    depth: usize,
+    /// `--dead`: list the largest unreachable sections.
+    show_dead: bool,
+}
+
+/// The ELF `SHF_GNU_RETAIN` flag: sections marked `KEEP` in the link and
+/// never eligible for `--gc-sections` removal.
+const SHF_GNU_RETAIN: u64 = 0x200000;
+
+/// Set of sections reachable from the program's roots (entry point,
+/// interrupt vectors, exported/`KEEP` symbols), found by a BFS over ELF
+/// relocations. Anything left unvisited is what `--gc-sections` would
+/// have stripped.
+struct Reachability {
+    live: HashSet<SectionIndex>,
+}
+
+impl Reachability {
+    fn compute<'data: 'file, 'file, O>(object: &'file O) -> Self
+    where
+        O: object::Object<'data, 'file> + 'file,
+    {
+        let mut seen = HashSet::new();
+        let mut todo = VecDeque::new();
+
+        let mut root = |idx: Option<SectionIndex>| {
+            if let Some(idx) = idx.filter(|idx| seen.insert(*idx)) {
+                todo.push_back(idx);
+            }
+        };
+
+        if let Some(sec) = object
+            .sections()
+            .find(|s| s.address() <= object.entry() && object.entry() < s.address() + s.size())
+        {
+            root(Some(sec.index()));
+        }
+
+        for sym in object.symbols() {
+            // `is_global()` is just "not STB_LOCAL" — true for nearly every
+            // ordinary function, which would make almost everything a root.
+            // Dynamic scope (visible outside this object) is what actually
+            // corresponds to "exported".
+            let is_root = sym
+                .name()
+                .is_ok_and(|name| name == "__VECTORS" || name.contains("interrupt"))
+                || sym.scope() == object::SymbolScope::Dynamic;
+            if is_root {
+                root(sym.section_index());
+            }
+        }
+
+        for sec in object.sections() {
+            let retained = matches!(
+                sec.flags(),
+                object::SectionFlags::Elf { sh_flags } if sh_flags & SHF_GNU_RETAIN != 0
+            );
+            if retained {
+                root(Some(sec.index()));
+            }
+        }
+
+        while let Some(idx) = todo.pop_front() {
+            let Ok(sec) = object.section_by_index(idx) else {
+                continue;
+            };
+            for (_offset, reloc) in sec.relocations() {
+                let target = match reloc.target() {
+                    RelocationTarget::Symbol(sym_idx) => object
+                        .symbol_by_index(sym_idx)
+                        .ok()
+                        .and_then(|sym| sym.section_index()),
+                    RelocationTarget::Section(sec_idx) => Some(sec_idx),
+                    _ => None,
+                };
+                if let Some(target) = target.filter(|t| seen.insert(*t)) {
+                    todo.push_back(target);
+                }
+            }
+        }
+
+        Self { live: seen }
+    }
+
+    fn is_live(&self, sec: SectionIndex) -> bool {
+        self.live.contains(&sec)
+    }
+}
+
+#[cfg(test)]
+mod reachability_tests {
+    use object::write::{Object as WriteObject, Relocation, Symbol, SymbolSection};
+    use object::{
+        Architecture, BinaryFormat, Endianness, ObjectSection, RelocationEncoding, RelocationFlags,
+        RelocationKind, SectionKind, SymbolFlags, SymbolKind, SymbolScope,
+    };
+
+    use super::Reachability;
+
+    fn base_object() -> WriteObject<'static> {
+        WriteObject::new(BinaryFormat::Elf, Architecture::X86_64, Endianness::Little)
+    }
+
+    #[test]
+    fn roots_on_dynamic_scope_and_walks_relocations() {
+        let mut obj = base_object();
 
-    syms.sort_by(|&(aa, an, _), &(ba, bn, _)| aa.cmp(&ba).then(an.cmp(bn).reverse()));
+        // A decoy section takes the entry-point slot (every section here has
+        // address 0, so the first one found is the entry-point section);
+        // `.text.root` can then only be live through its dynamic-scope symbol.
+        let decoy = obj.add_section(vec![], b".text.decoy".to_vec(), SectionKind::Text);
+        obj.append_section_data(decoy, &[0u8; 4], 4);
 
-    // for (addr, name, sym) in syms {
-    //     println!("{addr:x} {name} {sym:?}")
-    // }
+        let root_sec = obj.add_section(vec![], b".text.root".to_vec(), SectionKind::Text);
+        obj.append_section_data(root_sec, &[0u8; 8], 4);
+        let live_sec = obj.add_section(vec![], b".text.live".to_vec(), SectionKind::Text);
+        obj.append_section_data(live_sec, &[0u8; 4], 4);
+        let dead_sec = obj.add_section(vec![], b".text.dead".to_vec(), SectionKind::Text);
+        obj.append_section_data(dead_sec, &[0u8; 4], 4);
 
+        obj.add_symbol(Symbol {
+            name: b"exported_entry".to_vec(),
+            value: 0,
+            size: 8,
+            kind: SymbolKind::Text,
+            scope: SymbolScope::Dynamic,
+            weak: false,
+            section: SymbolSection::Section(root_sec),
+            flags: SymbolFlags::None,
+        });
+        let live_sym = obj.add_symbol(Symbol {
+            name: b"live_target".to_vec(),
+            value: 0,
+            size: 4,
+            kind: SymbolKind::Text,
+            scope: SymbolScope::Compilation,
+            weak: false,
+            section: SymbolSection::Section(live_sec),
+            flags: SymbolFlags::None,
+        });
+
+        obj.add_relocation(
+            root_sec,
+            Relocation {
+                offset: 0,
+                symbol: live_sym,
+                addend: 0,
+                flags: RelocationFlags::Generic {
+                    kind: RelocationKind::Absolute,
+                    encoding: RelocationEncoding::Generic,
+                    size: 64,
+                },
+            },
+        )
+        .expect("add relocation");
+
+        let data = obj.write().expect("write object");
+        let file = object::File::parse(data.as_slice()).expect("parse");
+        let reach = Reachability::compute(&file);
+
+        let section_index = |name: &str| {
+            file.sections()
+                .find(|s| s.name() == Ok(name))
+                .expect("section present")
+                .index()
+        };
+
+        assert!(reach.is_live(section_index(".text.root")));
+        assert!(reach.is_live(section_index(".text.live")));
+        assert!(!reach.is_live(section_index(".text.dead")));
+    }
+
+    #[test]
+    fn ordinary_global_symbols_are_not_roots() {
+        let mut obj = base_object();
+
+        // A decoy section occupies the entry-point slot (every section in an
+        // unlinked relocatable object has address 0, so the first one found
+        // is always "the" entry-point section); this keeps that heuristic
+        // from confounding the symbol-scope check below.
+        let decoy = obj.add_section(vec![], b".text.decoy".to_vec(), SectionKind::Text);
+        obj.append_section_data(decoy, &[0u8; 4], 4);
+
+        let sec = obj.add_section(vec![], b".text.unreferenced".to_vec(), SectionKind::Text);
+        obj.append_section_data(sec, &[0u8; 4], 4);
+
+        // An ordinary externally-linkable function: global/weak ELF binding,
+        // but not exported (no dynamic symbol visibility) — the case the old
+        // `is_global()`-based root selection got wrong.
+        obj.add_symbol(Symbol {
+            name: b"some_internal_fn".to_vec(),
+            value: 0,
+            size: 4,
+            kind: SymbolKind::Text,
+            scope: SymbolScope::Linkage,
+            weak: false,
+            section: SymbolSection::Section(sec),
+            flags: SymbolFlags::None,
+        });
+
+        let data = obj.write().expect("write object");
+        let file = object::File::parse(data.as_slice()).expect("parse");
+        let reach = Reachability::compute(&file);
+
+        let idx = file
+            .sections()
+            .find(|s| s.name() == Ok(".text.unreferenced"))
+            .expect("section present")
+            .index();
+
+        assert!(!reach.is_live(idx));
+    }
+
+    #[test]
+    fn retained_sections_are_roots_even_without_an_exported_symbol() {
+        let mut obj = base_object();
+
+        // Same decoy-section trick as above: keeps the entry-point heuristic
+        // from being the reason this section ends up live, so the assertion
+        // actually exercises SHF_GNU_RETAIN.
+        let decoy = obj.add_section(vec![], b".text.decoy".to_vec(), SectionKind::Text);
+        obj.append_section_data(decoy, &[0u8; 4], 4);
+
+        let sec = obj.add_section(vec![], b".text.kept".to_vec(), SectionKind::Text);
+        obj.append_section_data(sec, &[0u8; 4], 4);
+        obj.section_mut(sec).flags = object::write::SectionFlags::Elf {
+            sh_flags: 0x200000, // SHF_GNU_RETAIN
+        };
+
+        let data = obj.write().expect("write object");
+        let file = object::File::parse(data.as_slice()).expect("parse");
+        let reach = Reachability::compute(&file);
+
+        let idx = file
+            .sections()
+            .find(|s| s.name() == Ok(".text.kept"))
+            .expect("section present")
+            .index();
+
+        assert!(reach.is_live(idx));
+    }
+}
+
+fn print_alloc<'data: 'file, 'file, 'str, Object>(
+    object: &'file Object,
+    reach: &Reachability,
+    r: Region,
+    name: &'str str,
+    opts: &ReportOpts,
+) -> Result<RegionTotals, RegionOverflow>
+where
+    Object: object::Object<'data, 'file> + 'file,
+{
     let rsz = r.end - r.start;
     let mut total = Bytes(0u64);
+    let mut flash_total = Bytes(0u64);
+    let mut reachable = Bytes(0u64);
     let mut last = r.start;
+    let mut dead: Vec<(Bytes, String)> = Vec::new();
     println!("{name} allocation:");
     println!(
-        "{:21}\t{:7}\t({:^13})\t{:>8}\t{:<}",
-        "addr", "size", "bytes", "%", "name"
+        "{:21}\t{:7}\t{:7}\t({:^13})\t{:>8}\t{:<}",
+        "addr", "ram", "flash", "bytes", "%", "name"
     );
     for sec in object.sections().filter(|s| r.contains(&s.address())) {
-        let align = sec.align();
-        // align 1 -> x...xxx y...yyy -> 00
-        // align 2 -> x...xx0 y...yyy -> 2 - 0y
-        // align 4 -> x...x00 y...yyy -> 4 - yy
-
-        let mask = align - 1;
-        // let mask = (mask - 1) & !mask; // 0000 or 0011
+        let align = Align::new(sec.align());
+        let pad = align.padding_after(last);
 
-        let pad = last & mask;
-        if pad > 0 {
+        if pad.0 > 0 {
+            let pad_alloc = pad.0 as f64 / rsz as f64 * 100f64;
             println!(
-                "{:>21}\t0x{align:05x}\t{:>11}\t{:>8}\t{:<}",
-                "(padding)", "—", "—", "—"
+                "{:>21}\t0x{pad:05x}\t{:7}\t({pad:6})\t{pad_alloc:7.3}%\t{:<}",
+                "(padding)", "—", "—"
             );
         }
         let name = sec.name().expect("non utf-8 section name");
@@ -66,21 +383,291 @@ where
         let end = start + sz;
         let alloc = sz.0 as f64 / rsz as f64 * 100f64;
 
-        println!("0x{start:x}-0x{end:x}\t0x{sz:05x}\t({sz:6})\t{alloc:7.3}%\t{name}");
-        total += sz + pad;
+        // NOBITS sections (.bss, .uninit) are zero-initialized by the
+        // runtime and carry no on-disk contents, so they cost RAM but
+        // nothing in the flash load image; PROGBITS sections (.data, ...)
+        // cost both.
+        let flash_sz = if sec.file_range().is_some() {
+            sz
+        } else {
+            Bytes(0)
+        };
+
+        println!(
+            "0x{start:x}-0x{end:x}\t0x{sz:05x}\t0x{flash_sz:05x}\t({sz:6})\t{alloc:7.3}%\t{name}"
+        );
+        total += pad;
+        total += sz;
+        flash_total += flash_sz;
+        if reach.is_live(sec.index()) {
+            reachable += sz;
+        } else {
+            dead.push((sz, name.to_string()));
+        }
         last = end;
     }
 
     let alloc = total.0 as f64 / rsz as f64 * 100f64;
     println!(
-        "{:>21}\t0x{total:05x}\t({total:6})\t{alloc:7.3}%\t—",
+        "{:>21}\t0x{total:05x}\t0x{flash_total:05x}\t({total:6})\t{alloc:7.3}%\t—",
         "total"
-    )
+    );
+
+    let unreachable = Bytes(total.0.saturating_sub(reachable.0));
+    println!(
+        "{:>21}\t0x{reachable:05x}\t{:7}\t({reachable:6})\t{:7.3}%\t—",
+        "reachable",
+        "—",
+        reachable.0 as f64 / rsz as f64 * 100f64
+    );
+    println!(
+        "{:>21}\t0x{unreachable:05x}\t{:7}\t({unreachable:6})\t{:7.3}%\t—",
+        "potentially strippable",
+        "—",
+        unreachable.0 as f64 / rsz as f64 * 100f64
+    );
+
+    if opts.show_dead {
+        dead.sort_by_key(|(sz, _)| std::cmp::Reverse(sz.0));
+        println!("largest unreachable sections:");
+        for (sz, name) in dead.iter().take(10) {
+            println!("\t0x{sz:05x}\t({sz:6})\t{name}");
+        }
+    }
+
+    let tree = symbol_tree(object, r);
+    println!("{name} by symbol (crate/module):");
+    tree.print_children(1, opts);
+
+    if total.0 > rsz {
+        return Err(RegionOverflow {
+            region: name.to_string(),
+            total,
+            size: Bytes(rsz),
+        });
+    }
+
+    Ok(RegionTotals {
+        ram: total,
+        flash: flash_total,
+    })
+}
+
+/// A crate/module-qualified rollup of symbol sizes, built by demangling each
+/// symbol name and splitting it on `::`. Each node's `size` is the sum of
+/// everything beneath it, so printing stops at `opts.depth` without losing
+/// the total.
+#[derive(Default)]
+struct SymbolNode {
+    size: Bytes,
+    children: std::collections::HashMap<String, SymbolNode>,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+impl SymbolNode {
+    fn insert(&mut self, path: &[&str], size: Bytes) {
+        self.size += size;
+        if let [head, rest @ ..] = path {
+            self.children
+                .entry((*head).to_string())
+                .or_default()
+                .insert(rest, size);
+        }
+    }
+
+    fn print_children(&self, depth: usize, opts: &ReportOpts) {
+        let mut children = self.children.iter().collect::<Vec<_>>();
+        if opts.sort_size {
+            children.sort_by(|a, b| b.1.size.0.cmp(&a.1.size.0).then(a.0.cmp(b.0)));
+        } else {
+            children.sort_by(|a, b| a.0.cmp(b.0));
+        }
+        for (label, child) in children {
+            println!(
+                "{:>indent$}0x{:05x}\t({:6})\t{label}",
+                "",
+                child.size,
+                child.size,
+                indent = depth * 2 + 21
+            );
+            if depth < opts.depth {
+                child.print_children(depth + 1, opts);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod symbol_node_tests {
+    use super::{Bytes, SymbolNode};
+
+    #[test]
+    fn insert_rolls_sizes_up_through_every_ancestor() {
+        let mut root = SymbolNode::default();
+        root.insert(&["my_crate", "mod_a", "func_one"], Bytes(10));
+        root.insert(&["my_crate", "mod_a", "func_two"], Bytes(20));
+        root.insert(&["my_crate", "mod_b", "func_three"], Bytes(5));
+
+        assert_eq!(root.size, Bytes(35));
+
+        let my_crate = &root.children["my_crate"];
+        assert_eq!(my_crate.size, Bytes(35));
+        assert_eq!(my_crate.children["mod_a"].size, Bytes(30));
+        assert_eq!(
+            my_crate.children["mod_a"].children["func_one"].size,
+            Bytes(10)
+        );
+        assert_eq!(
+            my_crate.children["mod_a"].children["func_two"].size,
+            Bytes(20)
+        );
+        assert_eq!(my_crate.children["mod_b"].size, Bytes(5));
+    }
+
+    #[test]
+    fn a_node_keeps_its_full_rolled_up_size_regardless_of_print_depth() {
+        // `print_children` stops recursing past `opts.depth`, but that's a
+        // display choice made while walking the tree afterwards — it must
+        // not affect the sizes computed during `insert`.
+        let mut root = SymbolNode::default();
+        root.insert(&["a", "b", "c", "d"], Bytes(42));
+
+        assert_eq!(root.size, Bytes(42));
+        assert_eq!(root.children["a"].size, Bytes(42));
+        assert_eq!(root.children["a"].children["b"].size, Bytes(42));
+        assert_eq!(
+            root.children["a"].children["b"].children["c"].size,
+            Bytes(42)
+        );
+        assert_eq!(
+            root.children["a"].children["b"].children["c"].children["d"].size,
+            Bytes(42)
+        );
+    }
+
+    #[test]
+    fn insert_with_empty_path_only_contributes_to_self() {
+        let mut root = SymbolNode::default();
+        root.insert(&[], Bytes(7));
+
+        assert_eq!(root.size, Bytes(7));
+        assert!(root.children.is_empty());
+    }
+}
+
+/// Attribute each symbol in `r` a size by sorting symbol addresses within
+/// its section and taking the delta to the next symbol (or the section's
+/// end, for the last one), then roll the demangled, `::`-split names up
+/// into a crate/module tree. Only Rust's mangling scheme is understood; a
+/// C++ (Itanium `_ZN...`) symbol comes through undemangled as a single path
+/// segment.
+fn symbol_tree<'data: 'file, 'file, Object>(object: &'file Object, r: Region) -> SymbolNode
+where
+    Object: object::Object<'data, 'file> + 'file,
+{
+    let mut tree = SymbolNode::default();
+
+    for sec in object.sections().filter(|s| r.contains(&s.address())) {
+        let mut syms = object
+            .symbols()
+            .filter(|sym| {
+                sym.address() >= sec.address() && sym.address() < sec.address() + sec.size()
+            })
+            .filter_map(|sym| sym.name().map(|name| (sym.address(), name)).ok())
+            .collect::<Vec<_>>();
+        syms.sort_by_key(|&(addr, _)| addr);
+        syms.dedup_by_key(|&mut (addr, _)| addr);
+
+        let sec_end = sec.address() + sec.size();
+        for (i, &(addr, name)) in syms.iter().enumerate() {
+            let next = syms.get(i + 1).map(|&(a, _)| a).unwrap_or(sec_end);
+            let size = Bytes(next.saturating_sub(addr));
+
+            let demangled = rustc_demangle::demangle(name).to_string();
+            let path = demangled.split("::").collect::<Vec<_>>();
+            tree.insert(&path, size);
+        }
+    }
+
+    tree
+}
+
+/// A section's `sh_addralign`, kept distinct from a plain `u64` so the
+/// power-of-two invariant is checked once at construction instead of at
+/// every call site that computes padding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Align(u64);
+
+impl Align {
+    /// Per the ELF spec, `0` means the same thing as `1` (no alignment
+    /// constraint); anything else must be a power of two. Panics otherwise.
+    fn new(align: u64) -> Self {
+        let align = if align == 0 { 1 } else { align };
+        assert!(
+            align.is_power_of_two(),
+            "section alignment must be 0, 1, or a power of two, got {align:#x}"
+        );
+        Self(align)
+    }
+
+    /// Bytes of fill needed after `addr` to reach the next boundary aligned
+    /// to `self`.
+    fn padding_after(&self, addr: u64) -> Bytes {
+        Bytes((self.0 - (addr % self.0)) % self.0)
+    }
+}
+
+#[cfg(test)]
+mod align_tests {
+    use super::Align;
+
+    #[test]
+    fn zero_and_one_need_no_padding() {
+        assert_eq!(Align::new(0).padding_after(0x1001).0, 0);
+        assert_eq!(Align::new(1).padding_after(0x1001).0, 0);
+    }
+
+    #[test]
+    fn pads_up_to_the_next_boundary() {
+        assert_eq!(Align::new(4).padding_after(0x1001).0, 3);
+        assert_eq!(Align::new(4).padding_after(0x1004).0, 0);
+        assert_eq!(Align::new(0x100).padding_after(0x10).0, 0xF0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_non_power_of_two() {
+        Align::new(3);
+    }
+}
+
+/// A region whose sections plus padding no longer fit the bytes the linker
+/// gave it.
+struct RegionOverflow {
+    region: String,
+    total: Bytes,
+    size: Bytes,
+}
+
+impl std::fmt::Display for RegionOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let over = Bytes(self.total.0 - self.size.0);
+        write!(
+            f,
+            "{} region overflows by {over} bytes ({} used of {} available)",
+            self.region, self.total, self.size
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 struct Bytes(u64);
 
+impl Bytes {
+    fn checked_add(self, rhs: Bytes) -> Option<Bytes> {
+        self.0.checked_add(rhs.0).map(Bytes)
+    }
+}
+
 impl std::fmt::Display for Bytes {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // https://stackoverflow.com/questions/72544241/how-to-implement-display-while-respecting-width-fill-and-alignment
@@ -134,38 +721,260 @@ impl std::ops::Add<u64> for Bytes {
     }
 }
 
+impl std::ops::Add<Bytes> for Bytes {
+    type Output = Bytes;
+
+    fn add(self, rhs: Bytes) -> Self::Output {
+        let (Self(d), Self(rhs)) = (self, rhs);
+        Bytes(d + rhs)
+    }
+}
+
 impl std::ops::AddAssign<Bytes> for Bytes {
     fn add_assign(&mut self, rhs: Bytes) {
-        let (Self(d), Self(rhs)) = (self, rhs);
-        *d += rhs;
+        *self = self.checked_add(rhs).expect("byte count overflowed u64");
+    }
+}
+
+#[cfg(test)]
+mod bytes_tests {
+    use super::Bytes;
+
+    #[test]
+    fn checked_add_sums_in_range() {
+        assert_eq!(Bytes(1).checked_add(Bytes(2)).unwrap().0, 3);
+    }
+
+    #[test]
+    fn checked_add_none_on_overflow() {
+        assert!(Bytes(u64::MAX).checked_add(Bytes(1)).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_assign_panics_on_overflow() {
+        let mut total = Bytes(u64::MAX);
+        total += Bytes(1);
     }
 }
 
-type Region = std::ops::Range<u64>;
+pub(crate) type Region = std::ops::Range<u64>;
+
+/// Per-chip memory maps, so `print_alloc` isn't hardcoded to one target.
+///
+/// A [`MemMap`] is just a named, ordered list of [`Region`]s. It can come
+/// from a built-in table for a known chip ([`MemMap::for_chip`]) or be
+/// parsed straight out of a linker script's `MEMORY { }` block
+/// ([`MemMap::from_linker_script`]), so the tool works for any target
+/// without recompiling.
+mod memmap {
+    use super::Region;
+
+    /// A named set of memory regions for one chip, in report order.
+    #[derive(Clone, Debug, Default)]
+    pub struct MemMap {
+        regions: Vec<(String, Region)>,
+    }
+
+    impl MemMap {
+        /// Iterate the regions in the order they should be reported.
+        pub fn regions(&self) -> impl Iterator<Item = (&str, Region)> + '_ {
+            self.regions
+                .iter()
+                .map(|(name, region)| (name.as_str(), region.clone()))
+        }
+
+        fn new(regions: Vec<(&str, Region)>) -> Self {
+            Self {
+                regions: regions
+                    .into_iter()
+                    .map(|(name, region)| (name.to_string(), region))
+                    .collect(),
+            }
+        }
+
+        /// The built-in map for `--chip <name>`, or `None` for a chip this
+        /// tool doesn't know about (pass `--memory <linker.x>` instead).
+        pub fn for_chip(chip: &str) -> Option<Self> {
+            // cf. https://github.com/rustbox/esp-hal/blob/8815e752506903bcc37fe884ad9d9c8fe00ae75d/esp32c3-hal/ld/db-esp32c3-memory.x
+            let esp32c3 = || {
+                Self::new(vec![
+                    ("DRAM", 0x3FC80000..(0x3FC80000 + 0x50000 + 0x600)),
+                    (
+                        "IRAM",
+                        (0x4037C000 + 0x4000)..((0x4037C000 + 0x4000) + (400 * 1024 - 0x400)),
+                    ),
+                ])
+            };
+            // cf. esp-hal's esp32c6-hal/ld/esp32c6/memory.x: a single 512K
+            // HP SRAM split into a low IRAM half and a high DRAM half.
+            let esp32c6 = || {
+                let split = 0x40800000 + 256 * 1024;
+                Self::new(vec![
+                    ("IRAM", 0x40800000..split),
+                    ("DRAM", split..(split + 256 * 1024)),
+                ])
+            };
+            // cf. esp-hal's esp32s3-hal/ld/esp32s3/memory.x
+            let esp32s3 = || {
+                Self::new(vec![
+                    ("DRAM", 0x3FC88000..(0x3FC88000 + 0x7A000)),
+                    ("IRAM", 0x40370000..(0x40370000 + 0x40000)),
+                ])
+            };
+
+            Some(match chip {
+                "esp32c3" => esp32c3(),
+                "esp32c6" => esp32c6(),
+                "esp32s3" => esp32s3(),
+                _ => return None,
+            })
+        }
+
+        /// Parse the `MEMORY { ... }` block of a GNU ld linker script:
+        ///
+        /// ```text
+        /// MEMORY
+        /// {
+        ///   dram_seg (RW) : ORIGIN = 0x3FC88000, LENGTH = 0x50000
+        ///   iram_seg (RX) : ORIGIN = 0x40370000, LENGTH = 320K
+        /// }
+        /// ```
+        pub fn from_linker_script(src: &str) -> Result<Self, String> {
+            let src = strip_comments(src);
+            let kw = src.find("MEMORY").ok_or("no MEMORY block found")?;
+            let open = src[kw..].find('{').ok_or("MEMORY block missing '{'")? + kw;
+            let close = src[open..].find('}').ok_or("MEMORY block missing '}'")? + open;
+            let body = &src[open + 1..close];
 
-// cf. https://github.com/rustbox/esp-hal/blob/8815e752506903bcc37fe884ad9d9c8fe00ae75d/esp32c3-hal/ld/db-esp32c3-memory.x
-const DRAM: Region = 0x3FC80000..(0x3FC80000 + 0x50000 + 0x600);
-const IRAM: Region = (0x4037C000 + 0x4000)..((0x4037C000 + 0x4000) + (400 * 1024 - 0x400));
-// const DROM: std::ops::Range<u64> = 0x3C000000..(0x3C000000 + 0x400000);
+            let mut regions = Vec::new();
+            for line in body.lines() {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
 
-// these are the symbols that get emitted at the various locations
-// const DRAM_REGIONS: &[[&str; 3]] = &[
-//     [".data", "_sdata", "_edata"],
-//     // .rwtext.dummy is where these actually get loaded
-//     // [".rwtext", "", ""],
-//     [".bss", "_sbss", "_ebss"],
-//     [".uninit", "_suninit", "_euninit"],
-//     [".heap", "_sheap", "_eheap"],
-//     // note that stacks grow towards lower addresses
-//     [".stack", "_estack", "_sstack"],
-// ];
+                let (name, rest) = line
+                    .split_once(':')
+                    .ok_or_else(|| format!("malformed MEMORY entry: {line:?}"))?;
+                let name = name
+                    .split_whitespace()
+                    .next()
+                    .ok_or_else(|| format!("malformed MEMORY entry: {line:?}"))?;
 
-// struct Region {
+                let origin = parse_field(rest, "ORIGIN")?;
+                let length = parse_field(rest, "LENGTH")?;
 
-// }
+                regions.push((name.to_string(), origin..(origin + length)));
+            }
+
+            if regions.is_empty() {
+                return Err("MEMORY block had no regions".to_string());
+            }
+
+            Ok(Self { regions })
+        }
+    }
+
+    /// Strip `/* ... */` block comments and `//` line comments the way a
+    /// real `memory.x` is written, so they don't get mistaken for malformed
+    /// `MEMORY` entries.
+    fn strip_comments(src: &str) -> String {
+        let mut out = String::with_capacity(src.len());
+        let mut chars = src.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '/' && chars.peek() == Some(&'*') {
+                chars.next();
+                while let Some(c) = chars.next() {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        break;
+                    }
+                }
+            } else if c == '/' && chars.peek() == Some(&'/') {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    /// Pull `NAME = <value>,` out of a `MEMORY` entry's attribute list and
+    /// parse the value as an integer, accepting a trailing `K`/`M`
+    /// multiplier the way `ld` scripts do.
+    fn parse_field(s: &str, name: &str) -> Result<u64, String> {
+        let at = s
+            .find(name)
+            .ok_or_else(|| format!("missing {name} in MEMORY entry"))?;
+        let value = s[at..]
+            .split_once('=')
+            .ok_or_else(|| format!("malformed {name} in MEMORY entry"))?
+            .1
+            .split(',')
+            .next()
+            .unwrap_or("")
+            .trim();
+
+        parse_int(value).ok_or_else(|| format!("malformed {name} value: {value:?}"))
+    }
+
+    fn parse_int(tok: &str) -> Option<u64> {
+        let (digits, mul) = match tok.as_bytes().last()? {
+            b'K' | b'k' => (&tok[..tok.len() - 1], 1024),
+            b'M' | b'm' => (&tok[..tok.len() - 1], 1024 * 1024),
+            _ => (tok, 1),
+        };
+
+        let value = if let Some(hex) = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+        {
+            u64::from_str_radix(hex, 16).ok()?
+        } else {
+            digits.parse().ok()?
+        };
+
+        value.checked_mul(mul)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::MemMap;
+
+        #[test]
+        fn parses_memory_block_with_comments() {
+            let script = "
+                /* this is a block comment
+                   spanning multiple lines */
+                MEMORY
+                {
+                  // low IRAM
+                  iram_seg (RX) : ORIGIN = 0x40370000, LENGTH = 0x40000
+                  dram_seg (RW) : ORIGIN = 0x3FC88000, LENGTH = 320K /* trailing comment */
+                }
+            ";
 
-// struct MemMap {
-//     DRAM: Region,
-// }
+            let map = MemMap::from_linker_script(script).expect("should parse");
+            let regions = map.regions().collect::<Vec<_>>();
 
-// const MEMORY: MemMap = MemMap {};
+            assert_eq!(
+                regions,
+                vec![
+                    ("iram_seg", 0x40370000..(0x40370000 + 0x40000)),
+                    ("dram_seg", 0x3FC88000..(0x3FC88000 + 320 * 1024)),
+                ]
+            );
+        }
+
+        #[test]
+        fn rejects_missing_memory_block() {
+            assert!(MemMap::from_linker_script("SECTIONS { }").is_err());
+        }
+    }
+}